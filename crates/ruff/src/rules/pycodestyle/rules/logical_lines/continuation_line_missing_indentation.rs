@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+
 use super::LogicalLine;
 use crate::checkers::logical_lines::LogicalLinesContext;
-use ruff_diagnostics::Violation;
+use ruff_diagnostics::{Diagnostic, Violation};
 use ruff_macros::{derive_message_formats, violation};
 use ruff_python_ast::token_kind::TokenKind;
+use ruff_source_file::Locator;
+use ruff_text_size::{TextRange, TextSize};
 
 /// ## What it does
 /// Checks for continuation lines not indented as far as they should be or indented too far.
@@ -31,11 +35,497 @@ impl Violation for MissingIndentation {
     }
 }
 
-/// E122
+/// ## What it does
+/// Checks for continuation lines that are under-indented for a hanging indent.
+#[violation]
+pub struct UnderIndentedForHangingIndent;
+
+impl Violation for UnderIndentedForHangingIndent {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line under-indented for hanging indent.")
+    }
+}
+
+/// ## What it does
+/// Checks for closing brackets that do not match the indentation of the line that
+/// opened the bracket.
+#[violation]
+pub struct ClosingBracketDoesNotMatchIndentation;
+
+impl Violation for ClosingBracketDoesNotMatchIndentation {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Closing bracket does not match indentation of opening line's bracket.")
+    }
+}
+
+/// ## What it does
+/// Checks for closing brackets that do not match the established visual indentation.
+#[violation]
+pub struct ClosingBracketDoesNotMatchVisualIndentation;
+
+impl Violation for ClosingBracketDoesNotMatchVisualIndentation {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Closing bracket does not match visual indentation.")
+    }
+}
+
+/// ## What it does
+/// Checks for continuation lines that are indented to the same level as the next
+/// logical line, by way of a hanging indent.
+#[violation]
+pub struct ContinuationLineSameIndentAsNextLogicalLine;
+
+impl Violation for ContinuationLineSameIndentAsNextLogicalLine {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line with same indent as next logical line.")
+    }
+}
+
+/// ## What it does
+/// Checks for continuation lines that are over-indented for a hanging indent.
+#[violation]
+pub struct OverIndentedForHangingIndent;
+
+impl Violation for OverIndentedForHangingIndent {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line over-indented for hanging indent.")
+    }
+}
+
+/// ## What it does
+/// Checks for continuation lines that are under-indented for a visual indent.
+#[violation]
+pub struct UnderIndentedForVisualIndent;
+
+impl Violation for UnderIndentedForVisualIndent {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line under-indented for visual indent.")
+    }
+}
+
+/// ## What it does
+/// Checks for continuation lines that are over-indented for a visual indent.
+#[violation]
+pub struct OverIndentedForVisualIndent;
+
+impl Violation for OverIndentedForVisualIndent {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line over-indented for visual indent.")
+    }
+}
+
+/// ## What it does
+/// Checks for visually indented lines with the same indent as the next logical line.
+#[violation]
+pub struct VisuallyIndentedLineSameIndentAsNextLogicalLine;
+
+impl Violation for VisuallyIndentedLineSameIndentAsNextLogicalLine {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Visually indented line with same indent as next logical line.")
+    }
+}
+
+/// ## What it does
+/// Checks for hanging indents whose depth doesn't match the hanging indent already
+/// established for the same bracket depth.
+#[violation]
+pub struct HangingIndentNotConsistent;
+
+impl Violation for HangingIndentNotConsistent {
+    #[derive_message_formats]
+    fn message(&self) -> String {
+        format!("Continuation line unaligned for hanging indent.")
+    }
+}
+
+/// The reason a column is a plausible place to line a continuation line up under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Chance {
+    /// The column lines up with an established visual indent.
+    Visual,
+    /// The column is the start of a (possibly implicitly concatenated) string.
+    Str,
+    /// The column is the start of a token whose text may reappear on a later row.
+    /// Only opening and closing brackets are recorded; operators are not.
+    Token(String),
+}
+
+/// The outcome of checking whether the current token's column is a plausible
+/// continuation-indent match: an established visual indent, a mere string/token
+/// lineup (ignored, but not promoted to a visual indent), or no match at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VisualIndent {
+    Yes,
+    StrOrToken,
+    No,
+}
+
+#[derive(Debug)]
+struct RowToken<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    range: TextRange,
+    /// Column (in characters) at which the token starts on its physical line.
+    start_col: usize,
+    /// Column (in characters) at which the token ends on its physical line.
+    end_col: usize,
+    /// Row (relative to the first row of the logical line) the token starts on.
+    start_row: usize,
+    /// Row the token ends on (multiline strings can span several rows).
+    end_row: usize,
+    /// Text of the physical line the token starts on.
+    line: &'a str,
+}
+
+/// Return the amount of indentation, expanding tabs to the next multiple of 8
+/// (matching pycodestyle's `expand_indent`).
+fn expand_indent(line: &str) -> usize {
+    let line = line.trim_end_matches(['\n', '\r']);
+
+    if !line.contains('\t') {
+        return line.len() - line.trim_start().len();
+    }
+
+    let mut indent = 0;
+    for ch in line.chars() {
+        if ch == '\t' {
+            indent = indent / 8 * 8 + 8;
+        } else if ch == ' ' {
+            indent += 1;
+        } else {
+            break;
+        }
+    }
+    indent
+}
+
+/// Split `logical_line` into per-physical-line token positions the way pycodestyle's
+/// tokenizer does, so that multi-line tokens (e.g. triple-quoted strings) report a
+/// column relative to the physical line they start and end on.
+fn tokenize<'a>(logical_line: &'a LogicalLine, locator: &'a Locator) -> Vec<RowToken<'a>> {
+    let mut rows = Vec::new();
+    let mut row = 0usize;
+    let mut physical_line_start = logical_line.first_token().unwrap().range.start();
+
+    for token in logical_line.tokens() {
+        let text = locator.slice(token.range);
+        let start_col = usize::from(token.range.start() - physical_line_start);
+        let line_end = locator.full_line_end(token.range.start());
+        let line = locator.slice(TextRange::new(physical_line_start, line_end));
+
+        let newlines_within = text.matches('\n').count();
+        let end_row = row + newlines_within;
+        let end_col = if newlines_within > 0 {
+            text.len() - text.rfind('\n').unwrap() - 1
+        } else {
+            start_col + text.len()
+        };
+
+        rows.push(RowToken {
+            kind: token.kind,
+            text,
+            range: token.range,
+            start_col,
+            end_col,
+            start_row: row,
+            end_row,
+            line,
+        });
+
+        if matches!(
+            token.kind,
+            TokenKind::Newline | TokenKind::NonLogicalNewline
+        ) {
+            row += 1;
+            physical_line_start = token.range.end();
+        } else if newlines_within > 0 {
+            row = end_row;
+            let last_newline = text.rfind('\n').unwrap();
+            physical_line_start = token.range.start() + TextSize::try_from(last_newline + 1).unwrap();
+        }
+    }
+
+    rows
+}
+
+/// E121, E122, E123, E124, E125, E126, E127, E128, E129, E131
+///
+/// Port of pycodestyle's `continued_indentation` check. Tracks, per bracket depth, the
+/// established visual-indent column, the accepted hanging-indent delta and the rows on
+/// which a bracket at that depth was opened, plus a map of columns that are plausible
+/// places to line a continuation line up under (`indent_chances`).
 pub(crate) fn continuation_line_missing_indentation(
     context: &mut LogicalLinesContext,
     logical_line: &LogicalLine,
+    locator: &Locator,
     indent_char: char,
     indent_size: usize,
 ) {
+    let tokens = tokenize(logical_line, locator);
+    let Some(last) = tokens.last() else {
+        return;
+    };
+    let nrows = 1 + last.start_row;
+    if nrows == 1 {
+        return;
+    }
+
+    let indent_next = logical_line.text().ends_with(':');
+    let indent_level = expand_indent(tokens.first().unwrap().line);
+
+    let valid_hangs: Vec<i64> = if indent_char != '\t' {
+        vec![indent_size as i64]
+    } else {
+        vec![indent_size as i64, 2 * indent_size as i64]
+    };
+
+    let mut depth = 0usize;
+    // Number of brackets opened on each row.
+    let mut open_brackets_on_row = vec![0u32; nrows];
+    let mut rel_indent = vec![0i64; nrows];
+    let mut open_rows: Vec<Vec<usize>> = vec![vec![0]];
+    let mut hangs: Vec<Option<i64>> = vec![None];
+    let mut indent_chances: HashMap<usize, Chance> = HashMap::new();
+    let mut indent: Vec<usize> = vec![indent_level];
+    let mut last_indent = indent_level;
+    let mut visual_indent = VisualIndent::No;
+    let mut hang: i64 = 0;
+    let mut row = 0usize;
+    // Line and token range of the last processed token, used for the end-of-loop
+    // E125/E129 check below, which must only run once, after all tokens are seen.
+    let mut last_line = "";
+    let mut last_token_range = TextRange::new(TextSize::new(0), TextSize::new(0));
+
+    for token in &tokens {
+        let is_newline = token.start_row > row;
+        if is_newline {
+            row = token.start_row;
+            last_indent = token.start_col;
+        }
+
+        if is_newline
+            && !matches!(
+                token.kind,
+                TokenKind::Newline | TokenKind::NonLogicalNewline
+            )
+        {
+            rel_indent[row] = expand_indent(token.line) as i64 - indent_level as i64;
+
+            let is_closing_bracket = matches!(
+                token.kind,
+                TokenKind::Rpar | TokenKind::Rsqb | TokenKind::Rbrace
+            );
+
+            let mut hanging_indent = false;
+            for &open_row in open_rows[depth].iter().rev() {
+                hang = rel_indent[row] - rel_indent[open_row];
+                hanging_indent = valid_hangs.contains(&hang);
+                if hanging_indent {
+                    break;
+                }
+            }
+            if let Some(depth_hang) = hangs[depth] {
+                hanging_indent = hang == depth_hang;
+            }
+
+            visual_indent = if is_closing_bracket || hang <= 0 {
+                VisualIndent::No
+            } else {
+                match indent_chances.get(&token.start_col) {
+                    Some(Chance::Visual) => VisualIndent::Yes,
+                    Some(Chance::Str) => VisualIndent::StrOrToken,
+                    Some(Chance::Token(text)) if text == token.text => VisualIndent::StrOrToken,
+                    _ => VisualIndent::No,
+                }
+            };
+
+            if is_closing_bracket && indent[depth] != 0 {
+                if token.start_col != indent[depth] {
+                    context.push_diagnostic(Diagnostic::new(
+                        ClosingBracketDoesNotMatchVisualIndentation,
+                        token.range,
+                    ));
+                }
+            } else if is_closing_bracket && hang == 0 {
+                // Matches the indentation of the opening bracket's line: OK.
+            } else if indent[depth] != 0 && token.start_col < indent[depth] {
+                if visual_indent != VisualIndent::Yes {
+                    context.push_diagnostic(Diagnostic::new(
+                        UnderIndentedForVisualIndent,
+                        token.range,
+                    ));
+                }
+            } else if hanging_indent || (indent_next && rel_indent[row] == 2 * indent_size as i64)
+            {
+                if is_closing_bracket {
+                    context.push_diagnostic(Diagnostic::new(
+                        ClosingBracketDoesNotMatchIndentation,
+                        token.range,
+                    ));
+                }
+                hangs[depth] = Some(hang);
+            } else if visual_indent == VisualIndent::Yes {
+                indent[depth] = token.start_col;
+            } else if visual_indent == VisualIndent::StrOrToken {
+                // The token lines up with a matching string or operator/bracket token
+                // from a previous row; ignore it without establishing a visual indent.
+            } else if hang <= 0 {
+                context.push_diagnostic(Diagnostic::new(MissingIndentation, token.range));
+            } else if indent[depth] != 0 {
+                context.push_diagnostic(Diagnostic::new(
+                    OverIndentedForVisualIndent,
+                    token.range,
+                ));
+            } else if !is_closing_bracket && hangs[depth].is_some_and(|h| h > 0) {
+                context.push_diagnostic(Diagnostic::new(HangingIndentNotConsistent, token.range));
+            } else {
+                hangs[depth] = Some(hang);
+                if hang > indent_size as i64 {
+                    context.push_diagnostic(Diagnostic::new(
+                        OverIndentedForHangingIndent,
+                        token.range,
+                    ));
+                } else {
+                    context.push_diagnostic(Diagnostic::new(
+                        UnderIndentedForHangingIndent,
+                        token.range,
+                    ));
+                }
+            }
+        }
+
+        // Look for visual indenting.
+        if open_brackets_on_row[row] > 0
+            && !matches!(token.kind, TokenKind::Newline | TokenKind::Comment)
+            && indent[depth] == 0
+        {
+            indent[depth] = token.start_col;
+            indent_chances.insert(token.start_col, Chance::Visual);
+        } else if matches!(token.kind, TokenKind::String | TokenKind::Comment) {
+            indent_chances.insert(token.start_col, Chance::Str);
+        } else if row == 0
+            && depth == 0
+            && matches!(
+                token.kind,
+                TokenKind::Assert | TokenKind::Raise | TokenKind::With
+            )
+        {
+            indent_chances.insert(token.end_col + 1, Chance::Visual);
+        } else if indent_chances.is_empty()
+            && row == 0
+            && depth == 0
+            && matches!(token.kind, TokenKind::If | TokenKind::Elif)
+        {
+            indent_chances.insert(token.end_col + 1, Chance::Visual);
+        } else if matches!(token.kind, TokenKind::Colon)
+            && token.line[token.end_col..].trim().is_empty()
+        {
+            open_rows[depth].push(row);
+        }
+
+        let is_opening_bracket = matches!(
+            token.kind,
+            TokenKind::Lpar | TokenKind::Lsqb | TokenKind::Lbrace
+        );
+        let is_closing_bracket = matches!(
+            token.kind,
+            TokenKind::Rpar | TokenKind::Rsqb | TokenKind::Rbrace
+        );
+
+        if is_opening_bracket {
+            depth += 1;
+            indent.push(0);
+            hangs.push(None);
+            if open_rows.len() == depth {
+                open_rows.push(Vec::new());
+            }
+            open_rows[depth].push(row);
+            open_brackets_on_row[row] += 1;
+        } else if is_closing_bracket && depth > 0 {
+            let popped_indent = indent.pop().unwrap();
+            let prev_indent = if popped_indent != 0 {
+                popped_indent
+            } else {
+                last_indent
+            };
+            hangs.pop();
+            for d in indent.iter_mut().take(depth) {
+                if *d > prev_indent {
+                    *d = 0;
+                }
+            }
+            indent_chances.retain(|&col, _| col < prev_indent);
+            open_rows.truncate(depth);
+            depth -= 1;
+            if depth > 0 {
+                indent_chances.insert(indent[depth], Chance::Visual);
+            }
+            for idx in (0..=row).rev() {
+                if open_brackets_on_row[idx] > 0 {
+                    open_brackets_on_row[idx] -= 1;
+                    break;
+                }
+            }
+        }
+
+        if (is_opening_bracket || is_closing_bracket)
+            && !indent_chances.contains_key(&token.start_col)
+        {
+            indent_chances.insert(token.start_col, Chance::Token(token.text.to_string()));
+        }
+
+        if token.start_row != token.end_row {
+            rel_indent[token.end_row] = rel_indent[row];
+        }
+
+        last_line = token.line;
+        last_token_range = token.range;
+    }
+
+    // A continuation line indented to exactly the same column as the block body that
+    // follows the logical line (if any) is visually indistinguishable from it. This
+    // only needs checking once per logical line, so it runs after the loop rather than
+    // once per token -- matching the `ruff_linter` port of this same check.
+    if indent_next && expand_indent(last_line) == indent_level + indent_size {
+        if visual_indent != VisualIndent::No {
+            context.push_diagnostic(Diagnostic::new(
+                VisuallyIndentedLineSameIndentAsNextLogicalLine,
+                last_token_range,
+            ));
+        } else {
+            context.push_diagnostic(Diagnostic::new(
+                ContinuationLineSameIndentAsNextLogicalLine,
+                last_token_range,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_indent;
+
+    #[test]
+    fn expand_indent_spaces() {
+        assert_eq!(expand_indent("    a = 1"), 4);
+    }
+
+    #[test]
+    fn expand_indent_tabs() {
+        assert_eq!(expand_indent("\ta = 1"), 8);
+        assert_eq!(expand_indent("\t\ta = 1"), 16);
+    }
+
+    #[test]
+    fn expand_indent_mixed() {
+        assert_eq!(expand_indent("  \ta = 1"), 8);
+    }
 }
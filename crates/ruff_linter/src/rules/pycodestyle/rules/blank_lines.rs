@@ -18,11 +18,7 @@ use ruff_text_size::TextSize;
 
 use crate::checkers::logical_lines::expand_indent;
 use crate::line_width::IndentWidth;
-
-/// Number of blank lines around top level classes and functions.
-const BLANK_LINES_TOP_LEVEL: u32 = 2;
-/// Number of blank lines around methods and nested classes and functions.
-const BLANK_LINES_METHOD_LEVEL: u32 = 1;
+use crate::rules::pycodestyle::settings::Settings;
 
 /// ## What it does
 /// Checks for missing blank lines between methods of a class.
@@ -57,15 +53,17 @@ const BLANK_LINES_METHOD_LEVEL: u32 = 1;
 #[violation]
 pub struct BlankLineBetweenMethods {
     actual_blank_lines: u32,
+    expected_blank_lines: u32,
 }
 
 impl AlwaysFixableViolation for BlankLineBetweenMethods {
     #[derive_message_formats]
     fn message(&self) -> String {
         let BlankLineBetweenMethods {
-            actual_blank_lines: nb_blank_lines,
+            actual_blank_lines,
+            expected_blank_lines,
         } = self;
-        format!("Expected {BLANK_LINES_METHOD_LEVEL:?} blank line, found {nb_blank_lines}")
+        format!("Expected {expected_blank_lines:?} blank line, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -105,16 +103,18 @@ impl AlwaysFixableViolation for BlankLineBetweenMethods {
 #[violation]
 pub struct BlankLinesTopLevel {
     actual_blank_lines: u32,
+    expected_blank_lines: u32,
 }
 
 impl AlwaysFixableViolation for BlankLinesTopLevel {
     #[derive_message_formats]
     fn message(&self) -> String {
         let BlankLinesTopLevel {
-            actual_blank_lines: nb_blank_lines,
+            actual_blank_lines,
+            expected_blank_lines,
         } = self;
 
-        format!("Expected {BLANK_LINES_TOP_LEVEL:?} blank lines, found {nb_blank_lines}")
+        format!("Expected {expected_blank_lines:?} blank lines, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -247,15 +247,19 @@ impl AlwaysFixableViolation for BlankLineAfterDecorator {
 #[violation]
 pub struct BlankLinesAfterFunctionOrClass {
     actual_blank_lines: u32,
+    expected_blank_lines: u32,
 }
 
 impl AlwaysFixableViolation for BlankLinesAfterFunctionOrClass {
     #[derive_message_formats]
     fn message(&self) -> String {
         let BlankLinesAfterFunctionOrClass {
-            actual_blank_lines: blank_lines,
+            actual_blank_lines,
+            expected_blank_lines,
         } = self;
-        format!("expected 2 blank lines after class or function definition, found ({blank_lines})")
+        format!(
+            "expected {expected_blank_lines} blank lines after class or function definition, found ({actual_blank_lines})"
+        )
     }
 
     fn fix_title(&self) -> String {
@@ -296,15 +300,17 @@ impl AlwaysFixableViolation for BlankLinesAfterFunctionOrClass {
 #[violation]
 pub struct BlankLinesBeforeNestedDefinition {
     actual_blank_lines: u32,
+    expected_blank_lines: u32,
 }
 
 impl AlwaysFixableViolation for BlankLinesBeforeNestedDefinition {
     #[derive_message_formats]
     fn message(&self) -> String {
         let BlankLinesBeforeNestedDefinition {
-            actual_blank_lines: blank_lines,
+            actual_blank_lines,
+            expected_blank_lines,
         } = self;
-        format!("Expected 1 blank line before a nested definition, found {blank_lines}")
+        format!("Expected {expected_blank_lines} blank line before a nested definition, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -323,14 +329,48 @@ fn is_top_level_token_or_decorator(token: TokenKind) -> bool {
     matches!(&token, TokenKind::Class | TokenKind::Def | TokenKind::At)
 }
 
+/// Returns `true` if `line` is a complete one-line def/class, i.e. its body lives on the
+/// same logical line instead of in an indented suite (`def f(): ...` rather than `def f():`),
+/// whether that inlined body is a single simple statement or several chained with `;`
+/// (`def f(): a = 1; return a`). Consecutive one-liners like this (stub definitions,
+/// `@overload` stacks, property groups) are allowed to stay tightly packed without the
+/// blank lines E301/E302/E306 would otherwise require.
+fn is_one_liner_definition(
+    kind: LogicalLineKind,
+    last_token: TokenKind,
+    has_semicolon: bool,
+) -> bool {
+    matches!(kind, LogicalLineKind::Function | LogicalLineKind::Class)
+        && (last_token != TokenKind::Colon || has_semicolon)
+}
+
+/// Build the single `Edit` that normalizes a blank-line region to exactly `desired_count`
+/// line endings, replacing whatever blank lines currently occupy `region` in one shot.
+/// Routing the E303-E305 fixes, and the E301/E302 fixes whose gap holds no comment, through
+/// this same replacement (rather than mixing ad hoc insertions and deletions; E301/E302 still
+/// insert above a comment block that separates the definition from the previous statement,
+/// and E306 inserts) keeps the edits from overlapping when a gap is both
+/// under- and over-spaced by different rules, and makes `--fix` idempotent: re-running it
+/// on the normalized output always recomputes the same `desired_count` and produces a no-op.
+fn blank_lines_fix(newline: &str, region: TextRange, desired_count: u32) -> Edit {
+    Edit::range_replacement(newline.repeat(desired_count as usize), region)
+}
+
 #[derive(Debug)]
 struct LogicalLineInfo {
     kind: LogicalLineKind,
     first_token_range: TextRange,
 
-    // The token's kind right before the newline ending the logical line.
+    // The kind of the last non-trivia token on the logical line (trailing comments are
+    // skipped), e.g. `Colon` for a header whose suite is on the following lines, or the
+    // trailing statement's own last token -- `Semi` included -- for an inline one-liner body.
     last_token: TokenKind,
 
+    /// Whether the logical line contains a top-level (outside any brackets) `;`, i.e. it
+    /// chains two or more simple statements, interior or trailing
+    /// (`a = 1; b = 2` or `def f(): a = 1; return a;`).
+    has_semicolon: bool,
+
     // The end of the logical line including the newline.
     logical_line_end: TextSize,
     is_comment_only: bool,
@@ -344,6 +384,10 @@ struct LogicalLineInfo {
     /// It is also used to match the results of pydocstyle.
     preceding_blank_lines: u32,
     preceding_blank_characters: usize,
+    /// Whether a form feed (`\f`) character appears among the blank lines preceding this line.
+    /// pycodestyle treats a form feed as an explicit section separator, so it is exempted
+    /// from the E303/E305 blank-line counting it would otherwise trip.
+    has_form_feed: bool,
 }
 
 /// Iterator that processes tokens until a full logical line (or comment line) is "built".
@@ -383,8 +427,11 @@ impl<'a> Iterator for LinePreprocessor<'a> {
         let mut current_blank_lines = 0u32;
         // Number of blank characters in the blank lines (\n vs \r\n for example).
         let mut current_blank_characters: usize = 0;
+        // Whether a form feed was seen among the current run of blank lines.
+        let mut current_has_form_feed = false;
         let mut logical_line_start: Option<(LogicalLineKind, TextRange)> = None;
         let mut last_token: TokenKind = TokenKind::EndOfFile;
+        let mut has_semicolon = false;
         let mut parens = 0u32;
 
         while let Some(result) = self.tokens.next() {
@@ -409,8 +456,13 @@ impl<'a> Iterator for LinePreprocessor<'a> {
                 if token_kind == TokenKind::NonLogicalNewline {
                     current_blank_lines += 1;
                     current_blank_characters += range.len().to_usize();
-                    // self.current_blank_characters +=
-                    //     range.end().to_usize() - first_range.start().to_usize() + 1;
+                    // The lexer skips a form feed as whitespace, so search the whole
+                    // blank line rather than only the newline token's own text.
+                    let blank_line =
+                        TextRange::new(self.locator.line_start(range.start()), range.end());
+                    if self.locator.slice(blank_line).contains('\x0c') {
+                        current_has_form_feed = true;
+                    }
 
                     continue;
                 }
@@ -452,6 +504,9 @@ impl<'a> Iterator for LinePreprocessor<'a> {
                 TokenKind::Rbrace | TokenKind::Rpar | TokenKind::Rsqb => {
                     parens = parens.saturating_sub(1);
                 }
+                TokenKind::Semi if parens == 0 => {
+                    has_semicolon = true;
+                }
                 TokenKind::Newline | TokenKind::NonLogicalNewline if parens == 0 => {
                     let last_token_end = range.end();
 
@@ -470,6 +525,7 @@ impl<'a> Iterator for LinePreprocessor<'a> {
                         kind: logical_line_kind,
                         first_token_range,
                         last_token,
+                        has_semicolon,
                         logical_line_end: last_token_end,
                         is_comment_only: line_is_comment_only,
                         is_docstring,
@@ -477,6 +533,7 @@ impl<'a> Iterator for LinePreprocessor<'a> {
                         blank_lines: current_blank_lines,
                         preceding_blank_lines: self.preceding_blank_lines,
                         preceding_blank_characters: current_blank_characters,
+                        has_form_feed: current_has_form_feed,
                     };
 
                     if !line_is_comment_only {
@@ -487,7 +544,11 @@ impl<'a> Iterator for LinePreprocessor<'a> {
                 _ => {}
             }
 
-            last_token = token_kind;
+            // Trailing comments (e.g. `class Foo:  # noqa`) must not shadow the real last
+            // statement token, or a header's trailing `Colon` would look like a one-liner.
+            if !token_kind.is_trivia() {
+                last_token = token_kind;
+            }
         }
 
         None
@@ -525,44 +586,79 @@ pub(crate) struct BlankLinesChecker {
     /// to the second line instead of the first.
     last_non_comment_line_end: TextSize,
     previous_unindented_line_kind: Option<LogicalLineKind>,
+    /// Whether the previous non-comment logical line was a one-line class/function definition
+    /// (its body on the same line, e.g. `def f(): ...`). Used to allow consecutive one-liners
+    /// to stay tightly packed without triggering E301/E302/E306.
+    previous_line_was_one_liner_def: bool,
+    /// Stack of enclosing colon-terminated clause headers, keyed by the indentation of the
+    /// header itself. A frame is pushed when a logical line opens a suite (`last_token` is a
+    /// `Colon`) and popped once a later line dedents back to, or past, that header's indent.
+    /// The stack's length is the true nesting depth, unlike a raw `indent_length` comparison,
+    /// which can be fooled by mixed tabs/spaces or non-standard indent widths.
+    scope_stack: Vec<usize>,
 }
 
 impl BlankLinesChecker {
     /// E301, E302, E303, E304, E305, E306
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn check_lines(
         &mut self,
         tokens: &[LexResult],
         locator: &Locator,
         stylist: &Stylist,
         indent_width: IndentWidth,
+        settings: &Settings,
         diagnostics: &mut Vec<Diagnostic>,
     ) {
         let mut prev_indent_length: Option<usize> = None;
+        let mut prev_scope_depth: Option<usize> = None;
         let line_preprocessor = LinePreprocessor::new(tokens, locator, indent_width);
 
         for logical_line in line_preprocessor {
-            self.check_line(
+            let scope_depth = self.check_line(
                 &logical_line,
                 prev_indent_length,
+                prev_scope_depth,
                 locator,
                 stylist,
+                settings,
                 diagnostics,
             );
             if !logical_line.is_comment_only {
                 prev_indent_length = Some(logical_line.indent_length);
+                prev_scope_depth = Some(scope_depth);
             }
         }
     }
 
     #[allow(clippy::nonminimal_bool)]
+    #[allow(clippy::too_many_arguments)]
     fn check_line(
         &mut self,
         line: &LogicalLineInfo,
         prev_indent_length: Option<usize>,
+        prev_scope_depth: Option<usize>,
         locator: &Locator,
         stylist: &Stylist,
+        settings: &Settings,
         diagnostics: &mut Vec<Diagnostic>,
-    ) {
+    ) -> usize {
+        let top_level = settings.blank_lines_top_level;
+        let method_level = settings.blank_lines_method;
+        let nested_level = settings.blank_lines_nested;
+        let newline = settings.blank_lines_newline_style.resolve(stylist);
+
+        // Pop any enclosing headers that this line has dedented out of, then read off the
+        // true nesting depth -- this line's own header (if any) is pushed afterwards.
+        while self
+            .scope_stack
+            .last()
+            .is_some_and(|&opener_indent| line.indent_length <= opener_indent)
+        {
+            self.scope_stack.pop();
+        }
+        let scope_depth = self.scope_stack.len();
+
         match self.class_status {
             Status::Inside(nesting_indent) => {
                 if line.indent_length <= nesting_indent {
@@ -610,7 +706,7 @@ impl BlankLinesChecker {
 
         // Don't expect blank lines before the first non comment line.
         if self.is_not_first_logical_line {
-            if line.preceding_blank_lines == 0
+            if line.preceding_blank_lines < method_level
                 // Only applies to methods.
                 && matches!(line.kind,  LogicalLineKind::Function)
                 && matches!(self.class_status, Status::Inside(_))
@@ -620,27 +716,49 @@ impl BlankLinesChecker {
                 && prev_indent_length.is_some_and(|prev_indent_length| prev_indent_length >= line.indent_length)
                 // Allow following a decorator (if there is an error it will be triggered on the first decorator).
                 && !matches!(self.follows, Follows::Decorator)
+                // Allow groups of one-liners.
+                && !(self.previous_line_was_one_liner_def
+                    && is_one_liner_definition(line.kind, line.last_token, line.has_semicolon))
             {
                 // E301
                 let mut diagnostic = Diagnostic::new(
                     BlankLineBetweenMethods {
                         actual_blank_lines: line.preceding_blank_lines,
+                        expected_blank_lines: method_level,
                     },
                     line.first_token_range,
                 );
-                diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
-                    stylist.line_ending().as_str().to_string(),
-                    locator.line_start(self.last_non_comment_line_end),
-                )));
+
+                let end = locator.line_start(line.first_token_range.start());
+                let start = end
+                    - TextSize::try_from(line.preceding_blank_characters)
+                        .expect("Number of blank characters to be small.");
+                let fix = if start == self.last_non_comment_line_end {
+                    // No comment sits in the gap (the blank run starts right after the previous
+                    // statement): normalize it in one edit to exactly `method_level` newlines
+                    // instead of only padding what's already there.
+                    blank_lines_fix(&newline, TextRange::new(start, end), method_level)
+                } else {
+                    // A comment block (e.g. documenting the method) sits between the
+                    // previous statement and this def; pad the blank lines above the
+                    // comment block rather than between the comment and the def.
+                    Edit::insertion(
+                        newline.repeat((method_level - line.preceding_blank_lines) as usize),
+                        locator.line_start(self.last_non_comment_line_end),
+                    )
+                };
+                diagnostic.set_fix(Fix::safe_edit(fix));
 
                 diagnostics.push(diagnostic);
             }
 
-            if line.preceding_blank_lines < BLANK_LINES_TOP_LEVEL
+            if line.preceding_blank_lines < top_level
                 // Allow following a decorator (if there is an error it will be triggered on the first decorator).
                 && !matches!(self.follows, Follows::Decorator)
                 // Allow groups of one-liners.
-                && !(matches!(self.follows, Follows::Def) && !matches!(line.last_token, TokenKind::Colon))
+                && !(self.previous_line_was_one_liner_def
+                    && (matches!(line.kind, LogicalLineKind::Decorator)
+                        || is_one_liner_definition(line.kind, line.last_token, line.has_semicolon)))
                 // Only trigger on non-indented classes and functions (for example functions within an if are ignored)
                 && line.indent_length == 0
                 // Only apply to functions or classes.
@@ -650,23 +768,50 @@ impl BlankLinesChecker {
                 let mut diagnostic = Diagnostic::new(
                     BlankLinesTopLevel {
                         actual_blank_lines: line.preceding_blank_lines,
+                        expected_blank_lines: top_level,
                     },
                     line.first_token_range,
                 );
-                diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
-                    stylist
-                        .line_ending()
-                        .as_str()
-                        .to_string()
-                        .repeat((BLANK_LINES_TOP_LEVEL - line.preceding_blank_lines) as usize),
-                    locator.line_start(self.last_non_comment_line_end),
-                )));
+                let end = locator.line_start(line.first_token_range.start());
+                let start = end
+                    - TextSize::try_from(line.preceding_blank_characters)
+                        .expect("Number of blank characters to be small.");
+                let fix = if start == self.last_non_comment_line_end {
+                    // No comment sits in the gap (the blank run starts right after the previous
+                    // statement): normalize it in one edit to exactly `top_level` newlines
+                    // instead of only padding what's already there.
+                    blank_lines_fix(&newline, TextRange::new(start, end), top_level)
+                } else {
+                    // A comment separates the previous statement from this definition;
+                    // only pad the blank lines above it -- the comment's own spacing is
+                    // handled separately.
+                    Edit::insertion(
+                        newline.repeat((top_level - line.preceding_blank_lines) as usize),
+                        locator.line_start(self.last_non_comment_line_end),
+                    )
+                };
+                diagnostic.set_fix(Fix::safe_edit(fix));
 
                 diagnostics.push(diagnostic);
             }
 
-            if line.blank_lines > BLANK_LINES_TOP_LEVEL
-                || (line.indent_length > 0 && line.blank_lines > BLANK_LINES_METHOD_LEVEL)
+            // Inside a nested function or class, E306 floors the allowed gap at
+            // `nested_level` rather than `method_level`; E303's ceiling for the same
+            // context must agree, or the two can fire on the same blank-line run with
+            // contradictory target counts whenever `nested_level > method_level`.
+            let indented_level = if matches!(self.fn_status, Status::Inside(_)) {
+                nested_level
+            } else {
+                method_level
+            };
+
+            if !line.has_form_feed
+                && (line.blank_lines > top_level
+                    || (line.indent_length > 0 && line.blank_lines > indented_level))
+                // E304 takes precedence when the blank lines directly follow a decorator --
+                // pycodestyle treats these as mutually exclusive `elif` branches, and firing
+                // both here would emit two fixes over the same region.
+                && !matches!(self.follows, Follows::Decorator)
             {
                 // E303
                 let mut diagnostic = Diagnostic::new(
@@ -676,18 +821,22 @@ impl BlankLinesChecker {
                     line.first_token_range,
                 );
 
-                let chars_to_remove = if line.indent_length > 0 {
-                    u32::try_from(line.preceding_blank_characters)
-                        .expect("Number of blank characters to be small.")
-                        - BLANK_LINES_METHOD_LEVEL
+                let desired_count = if line.indent_length > 0 {
+                    indented_level
                 } else {
-                    u32::try_from(line.preceding_blank_characters)
-                        .expect("Number of blank characters to be small.")
-                        - BLANK_LINES_TOP_LEVEL
+                    top_level
                 };
                 let end = locator.line_start(line.first_token_range.start());
-                let start = end - TextSize::new(chars_to_remove);
-                diagnostic.set_fix(Fix::safe_edit(Edit::deletion(start, end)));
+                // The outer `!line.has_form_feed` guard above already means this blank
+                // span can't contain a form feed, so the whole span is safe to rewrite.
+                let start = end
+                    - TextSize::try_from(line.preceding_blank_characters)
+                        .expect("Number of blank characters to be small.");
+                diagnostic.set_fix(Fix::safe_edit(blank_lines_fix(
+                    &newline,
+                    TextRange::new(start, end),
+                    desired_count,
+                )));
 
                 diagnostics.push(diagnostic);
             }
@@ -697,21 +846,21 @@ impl BlankLinesChecker {
                 let mut diagnostic =
                     Diagnostic::new(BlankLineAfterDecorator, line.first_token_range);
 
-                let range = line.first_token_range;
-                diagnostic.set_fix(Fix::safe_edit(Edit::deletion(
-                    locator.line_start(range.start())
-                        - TextSize::new(
-                            line.preceding_blank_characters
-                                .try_into()
-                                .expect("Number of blank characters to be small."),
-                        ),
-                    locator.line_start(range.start()),
+                let end = locator.line_start(line.first_token_range.start());
+                let start = end
+                    - TextSize::try_from(line.preceding_blank_characters)
+                        .expect("Number of blank characters to be small.");
+                diagnostic.set_fix(Fix::safe_edit(blank_lines_fix(
+                    &newline,
+                    TextRange::new(start, end),
+                    0,
                 )));
 
                 diagnostics.push(diagnostic);
             }
 
-            if line.preceding_blank_lines < BLANK_LINES_TOP_LEVEL
+            if line.preceding_blank_lines < top_level
+                && !line.has_form_feed
                 && self
                     .previous_unindented_line_kind
                     .is_some_and(|kind| kind.is_top_level())
@@ -723,23 +872,25 @@ impl BlankLinesChecker {
                 let mut diagnostic = Diagnostic::new(
                     BlankLinesAfterFunctionOrClass {
                         actual_blank_lines: line.blank_lines,
+                        expected_blank_lines: top_level,
                     },
                     line.first_token_range,
                 );
 
-                diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
-                    stylist
-                        .line_ending()
-                        .as_str()
-                        .to_string()
-                        .repeat((BLANK_LINES_TOP_LEVEL - line.blank_lines) as usize),
-                    locator.line_start(line.first_token_range.start()),
+                let end = locator.line_start(line.first_token_range.start());
+                let start = end
+                    - TextSize::try_from(line.preceding_blank_characters)
+                        .expect("Number of blank characters to be small.");
+                diagnostic.set_fix(Fix::safe_edit(blank_lines_fix(
+                    &newline,
+                    TextRange::new(start, end),
+                    top_level,
                 )));
 
                 diagnostics.push(diagnostic);
             }
 
-            if line.preceding_blank_lines == 0
+            if line.preceding_blank_lines < nested_level
             // Only apply to nested functions.
                 && matches!(self.fn_status, Status::Inside(_))
                 && line.kind.is_top_level()
@@ -747,21 +898,25 @@ impl BlankLinesChecker {
                 && !matches!(self.follows, Follows::Decorator)
                 // The class's docstring can directly precede the first function.
                 && !matches!(self.follows, Follows::Docstring)
-                // Do not trigger when the def/class follows an "indenting token" (if/while/etc...).
-                && prev_indent_length.is_some_and(|prev_indent_length| prev_indent_length >= line.indent_length)
+                // Do not trigger when the def/class follows an "indenting token" (if/while/etc...):
+                // only a sibling statement at the same scope depth, not a parent header, should count.
+                && prev_scope_depth.is_some_and(|prev_scope_depth| prev_scope_depth >= scope_depth)
                 // Allow groups of one-liners.
-                && !(matches!(self.follows, Follows::Def) && line.last_token != TokenKind::Colon)
+                && !(self.previous_line_was_one_liner_def
+                    && (matches!(line.kind, LogicalLineKind::Decorator)
+                        || is_one_liner_definition(line.kind, line.last_token, line.has_semicolon)))
             {
                 // E306
                 let mut diagnostic = Diagnostic::new(
                     BlankLinesBeforeNestedDefinition {
                         actual_blank_lines: line.blank_lines,
+                        expected_blank_lines: nested_level,
                     },
                     line.first_token_range,
                 );
 
                 diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
-                    stylist.line_ending().as_str().to_string(),
+                    newline.repeat((nested_level - line.preceding_blank_lines) as usize),
                     locator.line_start(line.first_token_range.start()),
                 )));
 
@@ -803,7 +958,17 @@ impl BlankLinesChecker {
             if line.indent_length == 0 {
                 self.previous_unindented_line_kind = Some(line.kind);
             }
+
+            self.previous_line_was_one_liner_def =
+                is_one_liner_definition(line.kind, line.last_token, line.has_semicolon);
+
+            // A colon-terminated header opens a new suite one level deeper than `scope_depth`.
+            if line.last_token == TokenKind::Colon {
+                self.scope_stack.push(line.indent_length);
+            }
         }
+
+        scope_depth
     }
 }
 
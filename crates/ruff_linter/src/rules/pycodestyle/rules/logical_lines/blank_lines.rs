@@ -10,6 +10,7 @@ use ruff_source_file::Locator;
 use ruff_text_size::TextSize;
 
 use crate::checkers::logical_lines::LogicalLinesContext;
+use crate::rules::pycodestyle::settings::Settings;
 
 use super::LogicalLine;
 
@@ -57,16 +58,6 @@ impl Default for BlankLinesTrackingVars {
     }
 }
 
-/// Number of blank lines between various code parts.
-struct BlankLinesConfig;
-
-impl BlankLinesConfig {
-    /// Number of blank lines around top level classes and functions.
-    const TOP_LEVEL: u32 = 2;
-    /// Number of blank lines around methods and nested classes and functions.
-    const METHOD: u32 = 1;
-}
-
 /// ## What it does
 /// Checks for missing blank lines between methods of a class.
 ///
@@ -98,16 +89,19 @@ impl BlankLinesConfig {
 /// - [PEP 8](https://peps.python.org/pep-0008/#blank-lines)
 /// - [Flake 8 rule](https://www.flake8rules.com/rules/E301.html)
 #[violation]
-pub struct BlankLineBetweenMethods(pub u32);
+pub struct BlankLineBetweenMethods {
+    actual_blank_lines: u32,
+    expected_blank_lines: u32,
+}
 
 impl AlwaysFixableViolation for BlankLineBetweenMethods {
     #[derive_message_formats]
     fn message(&self) -> String {
-        let BlankLineBetweenMethods(nb_blank_lines) = self;
-        format!(
-            "Expected {:?} blank line, found {nb_blank_lines}",
-            BlankLinesConfig::METHOD
-        )
+        let BlankLineBetweenMethods {
+            actual_blank_lines,
+            expected_blank_lines,
+        } = self;
+        format!("Expected {expected_blank_lines:?} blank line, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -145,16 +139,19 @@ impl AlwaysFixableViolation for BlankLineBetweenMethods {
 /// - [PEP 8](https://peps.python.org/pep-0008/#blank-lines)
 /// - [Flake 8 rule](https://www.flake8rules.com/rules/E302.html)
 #[violation]
-pub struct BlankLinesTopLevel(pub u32);
+pub struct BlankLinesTopLevel {
+    actual_blank_lines: u32,
+    expected_blank_lines: u32,
+}
 
 impl AlwaysFixableViolation for BlankLinesTopLevel {
     #[derive_message_formats]
     fn message(&self) -> String {
-        let BlankLinesTopLevel(nb_blank_lines) = self;
-        format!(
-            "Expected {:?} blank lines, found {nb_blank_lines}",
-            BlankLinesConfig::TOP_LEVEL
-        )
+        let BlankLinesTopLevel {
+            actual_blank_lines,
+            expected_blank_lines,
+        } = self;
+        format!("Expected {expected_blank_lines:?} blank lines, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -281,13 +278,21 @@ impl AlwaysFixableViolation for BlankLineAfterDecorator {
 /// - [PEP 8](https://peps.python.org/pep-0008/#blank-lines)
 /// - [Flake 8 rule](https://www.flake8rules.com/rules/E305.html)
 #[violation]
-pub struct BlankLinesAfterFunctionOrClass(pub u32);
+pub struct BlankLinesAfterFunctionOrClass {
+    actual_blank_lines: u32,
+    expected_blank_lines: u32,
+}
 
 impl AlwaysFixableViolation for BlankLinesAfterFunctionOrClass {
     #[derive_message_formats]
     fn message(&self) -> String {
-        let BlankLinesAfterFunctionOrClass(blank_lines) = self;
-        format!("expected 2 blank lines after class or function definition, found ({blank_lines})")
+        let BlankLinesAfterFunctionOrClass {
+            actual_blank_lines,
+            expected_blank_lines,
+        } = self;
+        format!(
+            "expected {expected_blank_lines} blank lines after class or function definition, found ({actual_blank_lines})"
+        )
     }
 
     fn fix_title(&self) -> String {
@@ -327,13 +332,19 @@ impl AlwaysFixableViolation for BlankLinesAfterFunctionOrClass {
 /// - [PEP 8](https://peps.python.org/pep-0008/#blank-lines)
 /// - [Flake 8 rule](https://www.flake8rules.com/rules/E306.html)
 #[violation]
-pub struct BlankLinesBeforeNestedDefinition(pub u32);
+pub struct BlankLinesBeforeNestedDefinition {
+    actual_blank_lines: u32,
+    expected_blank_lines: u32,
+}
 
 impl AlwaysFixableViolation for BlankLinesBeforeNestedDefinition {
     #[derive_message_formats]
     fn message(&self) -> String {
-        let BlankLinesBeforeNestedDefinition(blank_lines) = self;
-        format!("Expected 1 blank line before a nested definition, found {blank_lines}")
+        let BlankLinesBeforeNestedDefinition {
+            actual_blank_lines,
+            expected_blank_lines,
+        } = self;
+        format!("Expected {expected_blank_lines} blank line before a nested definition, found {actual_blank_lines}")
     }
 
     fn fix_title(&self) -> String {
@@ -375,8 +386,13 @@ pub(crate) fn blank_lines(
     indent_size: usize,
     locator: &Locator,
     stylist: &Stylist,
+    settings: &Settings,
     context: &mut LogicalLinesContext,
 ) {
+    let top_level = settings.blank_lines_top_level;
+    let method = settings.blank_lines_method;
+    let nested = settings.blank_lines_nested;
+
     let line_is_comment_only = line.is_comment_only();
 
     if indent_level < tracked_vars.class_indent_level && tracked_vars.is_in_class {
@@ -429,7 +445,10 @@ pub(crate) fn blank_lines(
             {
                 // E301
                 let mut diagnostic = Diagnostic::new(
-                    BlankLineBetweenMethods(line.line.preceding_blank_lines),
+                    BlankLineBetweenMethods {
+                        actual_blank_lines: line.line.preceding_blank_lines,
+                        expected_blank_lines: method,
+                    },
                     token.range,
                 );
                 diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
@@ -440,7 +459,7 @@ pub(crate) fn blank_lines(
                 context.push_diagnostic(diagnostic);
             }
 
-            if line.line.preceding_blank_lines < BlankLinesConfig::TOP_LEVEL
+            if line.line.preceding_blank_lines < top_level
                 // Allow following a decorator (if there is an error it will be triggered on the first decorator).
                 && !tracked_vars.follows_decorator
                 // Allow groups of one-liners.
@@ -457,30 +476,35 @@ pub(crate) fn blank_lines(
             {
                 // E302
                 let mut diagnostic = Diagnostic::new(
-                    BlankLinesTopLevel(line.line.preceding_blank_lines),
+                    BlankLinesTopLevel {
+                        actual_blank_lines: line.line.preceding_blank_lines,
+                        expected_blank_lines: top_level,
+                    },
                     token.range,
                 );
                 diagnostic.set_fix(Fix::safe_edit(Edit::insertion(
-                    stylist.line_ending().as_str().to_string().repeat(
-                        (BlankLinesConfig::TOP_LEVEL - line.line.preceding_blank_lines) as usize,
-                    ),
+                    stylist
+                        .line_ending()
+                        .as_str()
+                        .to_string()
+                        .repeat((top_level - line.line.preceding_blank_lines) as usize),
                     locator.line_start(tracked_vars.last_non_comment_line_end),
                 )));
 
                 context.push_diagnostic(diagnostic);
             }
 
-            if line.line.blank_lines > BlankLinesConfig::TOP_LEVEL
-                || (indent_level > 0 && line.line.blank_lines > BlankLinesConfig::METHOD)
+            if line.line.blank_lines > top_level
+                || (indent_level > 0 && line.line.blank_lines > method)
             {
                 // E303
                 let mut diagnostic =
                     Diagnostic::new(TooManyBlankLines(line.line.blank_lines), token.range);
 
                 let chars_to_remove = if indent_level > 0 {
-                    line.line.preceding_blank_characters - BlankLinesConfig::METHOD
+                    line.line.preceding_blank_characters - method
                 } else {
-                    line.line.preceding_blank_characters - BlankLinesConfig::TOP_LEVEL
+                    line.line.preceding_blank_characters - top_level
                 };
                 let end = locator.line_start(token.range.start());
                 let start = end - TextSize::new(chars_to_remove);
@@ -503,7 +527,7 @@ pub(crate) fn blank_lines(
                 context.push_diagnostic(diagnostic);
             }
 
-            if line.line.preceding_blank_lines < BlankLinesConfig::TOP_LEVEL
+            if line.line.preceding_blank_lines < top_level
                 && is_top_level_token(tracked_vars.previous_unindented_token)
                 && indent_level == 0
                 && !line_is_comment_only
@@ -511,7 +535,10 @@ pub(crate) fn blank_lines(
             {
                 // E305
                 let mut diagnostic = Diagnostic::new(
-                    BlankLinesAfterFunctionOrClass(line.line.blank_lines),
+                    BlankLinesAfterFunctionOrClass {
+                        actual_blank_lines: line.line.blank_lines,
+                        expected_blank_lines: top_level,
+                    },
                     token.range,
                 );
 
@@ -520,7 +547,7 @@ pub(crate) fn blank_lines(
                         .line_ending()
                         .as_str()
                         .to_string()
-                        .repeat((BlankLinesConfig::TOP_LEVEL - line.line.blank_lines) as usize),
+                        .repeat((top_level - line.line.blank_lines) as usize),
                     locator.line_start(token.range.start()),
                 )));
 
@@ -547,7 +574,10 @@ pub(crate) fn blank_lines(
             {
                 // E306
                 let mut diagnostic = Diagnostic::new(
-                    BlankLinesBeforeNestedDefinition(line.line.blank_lines),
+                    BlankLinesBeforeNestedDefinition {
+                        actual_blank_lines: line.line.blank_lines,
+                        expected_blank_lines: nested,
+                    },
                     token.range,
                 );
 
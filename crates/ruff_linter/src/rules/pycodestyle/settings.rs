@@ -4,10 +4,70 @@ use ruff_macros::CacheKey;
 
 use crate::line_width::LineLength;
 
-#[derive(Debug, Default, CacheKey)]
+#[derive(Debug, CacheKey)]
 pub struct Settings {
     pub max_line_length: LineLength,
     pub max_doc_length: Option<LineLength>,
     pub ignore_overlong_task_comments: bool,
     pub hang_closing: bool,
+    /// Number of blank lines expected around top-level classes and functions (E302, E303, E305).
+    pub blank_lines_top_level: u32,
+    /// Number of blank lines expected between methods of a class (E301).
+    pub blank_lines_method: u32,
+    /// Number of blank lines expected before a definition nested inside a function body (E306).
+    pub blank_lines_nested: u32,
+    /// Line ending to use when a blank-line rule (E301-E306) inserts new blank lines.
+    pub blank_lines_newline_style: NewlineStyle,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_line_length: LineLength::default(),
+            max_doc_length: None,
+            ignore_overlong_task_comments: false,
+            hang_closing: false,
+            blank_lines_top_level: 2,
+            blank_lines_method: 1,
+            blank_lines_nested: 1,
+            blank_lines_newline_style: NewlineStyle::Auto,
+        }
+    }
+}
+
+/// Line ending style for blank lines inserted by a pycodestyle autofix.
+///
+/// In files that mix `\r\n` and `\n` endings, always trusting the single ending that
+/// `Stylist` detects for the whole file can inject the wrong terminator into a region
+/// that actually uses the other. These variants let that be overridden explicitly.
+#[derive(Debug, Default, Clone, Copy, CacheKey)]
+pub enum NewlineStyle {
+    /// Use the line ending detected by `Stylist` for the rest of the file.
+    #[default]
+    Auto,
+    /// Always insert `\n`.
+    Unix,
+    /// Always insert `\r\n`.
+    Windows,
+    /// Use the line ending native to the platform ruff is running on.
+    Native,
+}
+
+impl NewlineStyle {
+    /// Resolve this style to the literal line ending to insert, consulting `stylist` only
+    /// for [`NewlineStyle::Auto`].
+    pub fn resolve(self, stylist: &ruff_python_codegen::Stylist) -> String {
+        match self {
+            NewlineStyle::Auto => stylist.line_ending().as_str().to_string(),
+            NewlineStyle::Unix => "\n".to_string(),
+            NewlineStyle::Windows => "\r\n".to_string(),
+            NewlineStyle::Native => {
+                if cfg!(windows) {
+                    "\r\n".to_string()
+                } else {
+                    "\n".to_string()
+                }
+            }
+        }
+    }
 }